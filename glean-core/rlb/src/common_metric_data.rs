@@ -0,0 +1,53 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The metadata every metric type is constructed with, as used by
+//! `examples/prototype.rs`'s `glean_metrics` module.
+//!
+//! This mirrors `glean_core::CommonMetricData` field-for-field, except
+//! `category`/`name` are kept apart here rather than pre-joined into a
+//! single `identifier`, since that's what the (not-yet-generated-from-
+//! `metrics.yaml`) call sites in this crate's examples construct directly.
+
+/// How long a metric's value is kept before it's reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lifetime {
+    /// Reset with each ping sent containing the metric.
+    #[default]
+    Ping,
+    /// Reset when the application is restarted.
+    Application,
+    /// Reset when the user profile is reset.
+    User,
+}
+
+/// The metadata a metric is constructed with, shared across all metric
+/// types.
+#[derive(Debug, Clone, Default)]
+pub struct CommonMetricData {
+    /// The metric's name, as it appears in `metrics.yaml`.
+    pub name: String,
+    /// The category the metric belongs to, as it appears in `metrics.yaml`.
+    pub category: String,
+    /// The list of pings this metric is sent in.
+    pub send_in_pings: Vec<String>,
+    /// Whether this metric is disabled and should therefore not be recorded.
+    pub disabled: bool,
+    /// The metric's lifetime.
+    pub lifetime: Lifetime,
+    /// The label of a labeled metric's submetric, if this data describes one.
+    pub dynamic_label: Option<String>,
+}
+
+impl CommonMetricData {
+    /// Joins `category` and `name` the way `metrics.yaml`-generated
+    /// identifiers are formed elsewhere in Glean.
+    pub(crate) fn identifier(&self) -> String {
+        if self.category.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}.{}", self.category, self.name)
+        }
+    }
+}
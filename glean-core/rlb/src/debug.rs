@@ -0,0 +1,83 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Debug-only behavior: tagging pings for the debug view and logging the
+//! payload that would otherwise just be handed to the uploader.
+//!
+//! All of this is controllable both programmatically
+//! ([`set_debug_view_tag`], [`set_log_pings`]) and via environment
+//! variables read once at [`crate::initialize`] time, so a developer can
+//! flip them on without touching the embedding application's code -- the
+//! way `examples/prototype.rs` submits to `invalid-test-host` today.
+
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::upload::HeaderMap;
+
+/// The header used to route a tagged ping to the debug view.
+const DEBUG_VIEW_HEADER: &str = "X-Debug-ID";
+
+static DEBUG_VIEW_TAG: Mutex<Option<String>> = Mutex::new(None);
+static LOG_PINGS: AtomicBool = AtomicBool::new(false);
+
+/// Tags every subsequently assembled ping with `tag` so it's routed to the
+/// debug view instead of (or in addition to) normal processing.
+///
+/// Equivalent to setting the `GLEAN_DEBUG_VIEW_TAG` environment variable
+/// before [`crate::initialize`] runs.
+pub fn set_debug_view_tag(tag: &str) {
+    *DEBUG_VIEW_TAG.lock().unwrap() = Some(tag.to_string());
+}
+
+/// Enables or disables pretty-printing each assembled ping's JSON payload
+/// to the log before it's handed to the uploader.
+///
+/// Equivalent to setting `GLEAN_LOG_PINGS=true` before
+/// [`crate::initialize`] runs.
+pub fn set_log_pings(flag: bool) {
+    LOG_PINGS.store(flag, Ordering::Relaxed);
+}
+
+/// Reads `GLEAN_DEBUG_VIEW_TAG` and `GLEAN_LOG_PINGS`, seeding the same
+/// state [`set_debug_view_tag`]/[`set_log_pings`] would.
+///
+/// Called once from [`crate::initialize`]. `GLEAN_SEND_PING` is read here
+/// too and returned so the caller can submit that named ping immediately
+/// once the ping pipeline is up, matching the env-var-driven debug tools
+/// in the other Glean SDKs.
+pub(crate) fn apply_env_overrides() -> Option<String> {
+    if let Ok(tag) = env::var("GLEAN_DEBUG_VIEW_TAG") {
+        set_debug_view_tag(&tag);
+    }
+
+    if let Ok(flag) = env::var("GLEAN_LOG_PINGS") {
+        set_log_pings(flag.eq_ignore_ascii_case("true"));
+    }
+
+    env::var("GLEAN_SEND_PING").ok()
+}
+
+/// If a debug view tag is set, adds the `X-Debug-ID` header to `headers` so
+/// the ping gets routed to the debug view.
+pub(crate) fn tag_headers(headers: &mut HeaderMap) {
+    if let Some(tag) = DEBUG_VIEW_TAG.lock().unwrap().clone() {
+        headers.insert(DEBUG_VIEW_HEADER.to_string(), tag);
+    }
+}
+
+/// Pretty-prints `payload` to the log if [`set_log_pings`] (or
+/// `GLEAN_LOG_PINGS=true`) has enabled it.
+pub(crate) fn log_ping_payload(ping_type: &str, payload: &str) {
+    if !LOG_PINGS.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let pretty = serde_json::from_str::<serde_json::Value>(payload)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| payload.to_string());
+
+    log::info!("Glean ping '{ping_type}' payload:\n{pretty}");
+}
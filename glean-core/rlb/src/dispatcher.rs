@@ -0,0 +1,49 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A single-threaded task queue metric setters are recorded onto, so the
+//! calling application's thread never blocks on the (potentially slow) FFI
+//! round trip. Portable equivalent of `glean_core::dispatcher`'s
+//! `dispatch`-crate-based queue, scoped to this crate since this crate
+//! can't assume it's running on a platform that provides `libdispatch`.
+
+use std::sync::mpsc::{self, Sender};
+use std::sync::OnceLock;
+use std::thread;
+
+type Task = Box<dyn FnOnce() + Send + 'static>;
+
+fn sender() -> &'static Sender<Task> {
+    static SENDER: OnceLock<Sender<Task>> = OnceLock::new();
+    SENDER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<Task>();
+        thread::Builder::new()
+            .name("glean.dispatcher".into())
+            .spawn(move || {
+                for task in rx {
+                    task();
+                }
+            })
+            .expect("failed to spawn glean dispatcher thread");
+        tx
+    })
+}
+
+/// Queues `task` to run on the dispatcher thread, in the order it was
+/// launched relative to other queued tasks.
+pub(crate) fn launch(task: impl FnOnce() + Send + 'static) {
+    // The receiving end only ever goes away together with the sender
+    // stored in `SENDER`, so this can't actually fail.
+    let _ = sender().send(Box::new(task));
+}
+
+/// Blocks the calling thread until every task launched before this call has
+/// finished running.
+pub(crate) fn block_on_dispatcher() {
+    let (tx, rx) = mpsc::channel();
+    launch(move || {
+        let _ = tx.send(());
+    });
+    let _ = rx.recv();
+}
@@ -0,0 +1,273 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The Glean Rust language bindings (the `glean` crate embedding
+//! applications depend on directly, as in `examples/prototype.rs`).
+
+mod common_metric_data;
+mod debug;
+mod dispatcher;
+pub mod private;
+mod sys;
+mod upload;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::OnceCell;
+
+pub use common_metric_data::{CommonMetricData, Lifetime};
+pub use debug::{set_debug_view_tag, set_log_pings};
+pub use upload::{HeaderMap, PingUploader, UploadResult};
+
+pub(crate) use dispatcher::block_on_dispatcher;
+
+/// Wraps a metric constructor call so it runs against the shared [`sys`]
+/// handle, the way generated metric types do. See
+/// `private::dynamic::string`/`private::dynamic::timespan` for callers.
+#[macro_export]
+macro_rules! new_metric {
+    ($glean_fn:ident, $meta:expr $(, $arg:expr)*) => {
+        $crate::sys::with_glean(|glean| glean.$glean_fn($meta $(, $arg)*))
+    };
+}
+
+/// App- and device-level metadata the embedder supplies once at
+/// initialization, alongside [`Configuration`].
+#[derive(Debug, Clone)]
+pub struct ClientInfoMetrics {
+    /// The application's build identifier.
+    pub app_build: String,
+    /// The user visible version string (e.g. "1.0.3").
+    pub app_display_version: String,
+}
+
+impl ClientInfoMetrics {
+    /// Starts building a [`ClientInfoMetrics`] with sensible defaults for
+    /// everything.
+    pub fn builder() -> ClientInfoMetricsBuilder {
+        ClientInfoMetricsBuilder {
+            app_build: "Unknown".into(),
+            app_display_version: "Unknown".into(),
+        }
+    }
+}
+
+/// Builder for [`ClientInfoMetrics`]. See [`ClientInfoMetrics::builder`].
+pub struct ClientInfoMetricsBuilder {
+    app_build: String,
+    app_display_version: String,
+}
+
+impl ClientInfoMetricsBuilder {
+    /// Sets the application's build identifier. Defaults to `"Unknown"`.
+    pub fn app_build(mut self, value: impl Into<String>) -> Self {
+        self.app_build = value.into();
+        self
+    }
+
+    /// Sets the user visible app version. Defaults to `"Unknown"`.
+    pub fn app_display_version(mut self, value: impl Into<String>) -> Self {
+        self.app_display_version = value.into();
+        self
+    }
+
+    /// Builds the [`ClientInfoMetrics`].
+    pub fn build(self) -> ClientInfoMetrics {
+        ClientInfoMetrics {
+            app_build: self.app_build,
+            app_display_version: self.app_display_version,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Configuration {
+    /// Path to a directory to store all data in.
+    pub data_path: PathBuf,
+    /// The application ID (will be sanitized during initialization).
+    pub application_id: String,
+    /// Whether upload should be enabled.
+    pub upload_enabled: bool,
+    /// The maximum number of events to store before sending a ping containing events.
+    pub max_events: Option<u32>,
+    /// Whether Glean should delay persistence of data from metrics with ping lifetime.
+    pub delay_ping_lifetime_io: bool,
+    /// The release channel the application is on, if known.
+    pub channel: Option<String>,
+    /// The server endpoint pings are uploaded to. This value is not read when
+    /// uploading: the URL handed to the [`PingUploader`] is
+    /// `/submit/{ping_name}`, with no endpoint prepended.
+    pub server_endpoint: Option<String>,
+    /// The embedder-supplied HTTP client used to upload pings. `None` means
+    /// submitted pings are dropped, since this crate doesn't bundle an HTTP
+    /// client itself.
+    pub uploader: Option<Arc<dyn PingUploader>>,
+    /// Whether Glean should schedule "metrics" pings.
+    pub use_core_mps: bool,
+}
+
+impl Configuration {
+    /// Starts building a [`Configuration`] with the two fields that have no
+    /// sensible default, leaving the rest at their usual values.
+    ///
+    /// Building it field-by-field like this (rather than spelling out every
+    /// field in a struct literal, the way `examples/prototype.rs` does
+    /// today) means adding a new `Configuration` field later doesn't break
+    /// every existing call site.
+    pub fn builder(data_path: impl Into<PathBuf>, application_id: impl Into<String>) -> ConfigurationBuilder {
+        ConfigurationBuilder {
+            data_path: data_path.into(),
+            application_id: application_id.into(),
+            upload_enabled: true,
+            max_events: None,
+            delay_ping_lifetime_io: false,
+            channel: None,
+            server_endpoint: None,
+            uploader: None,
+            use_core_mps: false,
+        }
+    }
+}
+
+/// Builder for [`Configuration`]. See [`Configuration::builder`].
+pub struct ConfigurationBuilder {
+    data_path: PathBuf,
+    application_id: String,
+    upload_enabled: bool,
+    max_events: Option<u32>,
+    delay_ping_lifetime_io: bool,
+    channel: Option<String>,
+    server_endpoint: Option<String>,
+    uploader: Option<Arc<dyn PingUploader>>,
+    use_core_mps: bool,
+}
+
+impl ConfigurationBuilder {
+    /// Sets whether upload is enabled. Defaults to `true`.
+    pub fn upload_enabled(mut self, value: bool) -> Self {
+        self.upload_enabled = value;
+        self
+    }
+
+    /// Sets the maximum number of events to store before sending a ping
+    /// containing events. Unset by default, which uses Glean's own default.
+    pub fn max_events(mut self, value: u32) -> Self {
+        self.max_events = Some(value);
+        self
+    }
+
+    /// Sets whether Glean should delay persistence of ping-lifetime data.
+    /// Defaults to `false`.
+    pub fn delay_ping_lifetime_io(mut self, value: bool) -> Self {
+        self.delay_ping_lifetime_io = value;
+        self
+    }
+
+    /// Sets the release channel. Unset by default.
+    pub fn channel(mut self, value: impl Into<String>) -> Self {
+        self.channel = Some(value.into());
+        self
+    }
+
+    /// Sets the server endpoint pings are uploaded to. Unset by default.
+    pub fn server_endpoint(mut self, value: impl Into<String>) -> Self {
+        self.server_endpoint = Some(value.into());
+        self
+    }
+
+    /// Sets the embedder-supplied [`PingUploader`]. Unset by default.
+    pub fn uploader(mut self, value: Arc<dyn PingUploader>) -> Self {
+        self.uploader = Some(value);
+        self
+    }
+
+    /// Sets whether Glean should schedule "metrics" pings. Defaults to
+    /// `false`.
+    pub fn use_core_mps(mut self, value: bool) -> Self {
+        self.use_core_mps = value;
+        self
+    }
+
+    /// Builds the [`Configuration`].
+    pub fn build(self) -> Configuration {
+        Configuration {
+            data_path: self.data_path,
+            application_id: self.application_id,
+            upload_enabled: self.upload_enabled,
+            max_events: self.max_events,
+            delay_ping_lifetime_io: self.delay_ping_lifetime_io,
+            channel: self.channel,
+            server_endpoint: self.server_endpoint,
+            uploader: self.uploader,
+            use_core_mps: self.use_core_mps,
+        }
+    }
+}
+
+static UPLOAD_MANAGER: OnceCell<Arc<upload::UploadManager>> = OnceCell::new();
+
+/// The names of every [`private::PingType`] registered via
+/// [`register_ping_type`] so far.
+static REGISTERED_PINGS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+
+/// Registers `ping` so it can later be submitted by name, either directly
+/// via [`submit_ping_by_name`] or through [`private::PingType::submit`].
+pub fn register_ping_type(ping: &private::PingType) {
+    REGISTERED_PINGS.lock().unwrap().insert(ping.name.clone());
+}
+
+/// Submits the named ping. Its payload holds only `ping_info.reason`; no
+/// metric data is collected into it.
+///
+/// Does nothing but log a message if `ping_name` was never
+/// [`register_ping_type`]d, or if no [`PingUploader`] was configured via
+/// [`ConfigurationBuilder::uploader`].
+pub fn submit_ping_by_name(ping_name: &str, reason: Option<&str>) {
+    if !REGISTERED_PINGS.lock().unwrap().contains(ping_name) {
+        log::warn!("Ignoring attempt to submit unregistered ping '{ping_name}'");
+        return;
+    }
+
+    let Some(manager) = UPLOAD_MANAGER.get() else {
+        log::info!("No uploader configured; dropping ping '{ping_name}'");
+        return;
+    };
+
+    let body = serde_json::to_vec(&serde_json::json!({ "ping_info": { "reason": reason } }))
+        .unwrap_or_default();
+    manager.enqueue(
+        ping_name,
+        format!("/submit/{ping_name}"),
+        body,
+        HeaderMap::new(),
+    );
+}
+
+/// Initializes Glean. See `examples/prototype.rs` for a full example.
+///
+/// Also reads `GLEAN_DEBUG_VIEW_TAG`, `GLEAN_LOG_PINGS` and
+/// `GLEAN_SEND_PING` so debug behavior can be toggled without touching the
+/// embedding application's code. See [`set_debug_view_tag`] and
+/// [`set_log_pings`] for the programmatic equivalents.
+pub fn initialize(cfg: Configuration, _client_info: ClientInfoMetrics) {
+    let requested_ping = debug::apply_env_overrides();
+
+    if let Some(uploader) = cfg.uploader {
+        let _ = UPLOAD_MANAGER.set(upload::UploadManager::new(uploader, cfg.data_path));
+    }
+
+    if let Some(ping_name) = requested_ping {
+        submit_ping_by_name(&ping_name, None);
+    }
+}
+
+/// Shuts Glean down, persisting any pings that are still queued for upload
+/// (e.g. because `server_endpoint` was unreachable) so they aren't lost.
+pub fn shutdown() {
+    if let Some(manager) = UPLOAD_MANAGER.get() {
+        manager.persist();
+    }
+}
@@ -0,0 +1,42 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::{dispatcher, new_metric, CommonMetricData};
+
+/// This implements the developer facing API for recording counter metrics.
+///
+/// Instances of this class type are automatically generated by the parsers
+/// at build time, allowing developers to record values that were previously
+/// registered in the metrics.yaml file.
+#[derive(Clone)]
+pub struct CounterMetric {
+    id: u64,
+}
+
+impl CounterMetric {
+    /// The public constructor used by automatically generated metrics.
+    pub fn new(meta: CommonMetricData) -> Self {
+        Self {
+            id: new_metric!(glean_new_counter_metric, meta),
+        }
+    }
+
+    /// Adds `amount` to the counter. Amounts less than or equal to zero are
+    /// ignored.
+    pub fn add(&self, amount: i32) {
+        if amount <= 0 {
+            return;
+        }
+        let id = self.id;
+        dispatcher::launch(move || {
+            crate::sys::with_glean(|glean| unsafe { glean.glean_counter_add(id, amount) });
+        });
+    }
+
+    /// **Test-only API.** The metric's currently recorded value, if any.
+    pub fn test_get_value<'a, S: Into<Option<&'a str>>>(&self, _ping_name: S) -> Option<i64> {
+        crate::block_on_dispatcher();
+        crate::sys::with_glean(|glean| glean.glean_counter_test_get_value(self.id))
+    }
+}
@@ -0,0 +1,15 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Metric wrappers built on `glean_core`'s own metric types and traits,
+//! rather than this crate's local ones -- kept separate from
+//! [`super::counter`]/[`super::ping_type`] since they predate this crate's
+//! module tree being restored and still construct from
+//! `glean_core::CommonMetricData`.
+
+mod string;
+mod timespan;
+
+pub use string::StringMetric;
+pub use timespan::TimespanMetric;
@@ -3,30 +3,73 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use std::ffi::CString;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
 use glean_core::{ErrorType, Glean};
 use inherent::inherent;
 
 use crate::{dispatcher, new_metric};
 
+/// The default maximum length, in bytes, of a string metric's value before
+/// it gets truncated. Matches the default used for `StringMetric` in the
+/// other Glean SDKs.
+const DEFAULT_MAX_LENGTH: usize = 100;
+
 /// This implements the developer facing API for recording string metrics.
 ///
 /// Instances of this class type are automatically generated by the parsers
 /// at build time, allowing developers to record values that were previously
 /// registered in the metrics.yaml file.
 #[derive(Clone)]
-pub struct StringMetric(pub(crate) u64);
+pub struct StringMetric {
+    id: u64,
+    max_length: usize,
+    truncated_count: Arc<AtomicU32>,
+}
 
 impl StringMetric {
     /// The public constructor used by automatically generated metrics.
     pub fn new(meta: glean_core::CommonMetricData) -> Self {
-        Self(new_metric!(glean_new_string_metric, meta))
+        Self::with_max_length(meta, DEFAULT_MAX_LENGTH)
+    }
+
+    /// Like [`new`](Self::new), but with a caller-chosen maximum byte length
+    /// instead of the default of 100.
+    pub fn with_max_length(meta: glean_core::CommonMetricData, max_length: usize) -> Self {
+        Self {
+            id: new_metric!(glean_new_string_metric, meta),
+            max_length,
+            truncated_count: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Truncates `value` to `max_length` bytes on a UTF-8 char boundary,
+    /// recording an `ErrorType::InvalidOverflow` error if anything had to
+    /// be cut.
+    fn clamp(&self, value: std::string::String) -> std::string::String {
+        if value.len() <= self.max_length {
+            return value;
+        }
+
+        self.truncated_count.fetch_add(1, Ordering::Relaxed);
+        log::warn!(
+            "String value of length {} exceeds maximum length of {}, truncating. (ErrorType::InvalidOverflow)",
+            value.len(),
+            self.max_length
+        );
+
+        let mut end = self.max_length;
+        while end > 0 && !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        value[..end].to_string()
     }
 
     /// Internal only, synchronous API for setting a string value.
     pub(crate) fn set_sync<S: Into<std::string::String>>(&self, _glean: &Glean, value: S) {
-        let id = self.0;
-        let new_value = value.into();
+        let id = self.id;
+        let new_value = self.clamp(value.into());
         let value = CString::new(new_value).unwrap();
         crate::sys::with_glean(|glean| unsafe { glean.glean_string_set(id, value.as_ptr()) });
     }
@@ -35,13 +78,12 @@ impl StringMetric {
 #[inherent(pub)]
 impl glean_core::traits::String for StringMetric {
     fn set<S: Into<std::string::String>>(&self, value: S) {
-        let id = self.0;
-        let new_value = value.into();
+        let id = self.id;
+        let new_value = self.clamp(value.into());
         dispatcher::launch(move || {
             let value = CString::new(new_value).unwrap();
             crate::sys::with_glean(|glean| unsafe { glean.glean_string_set(id, value.as_ptr()) });
         });
-        todo!()
     }
 
     fn test_get_value<'a, S: Into<Option<&'a str>>>(
@@ -49,15 +91,18 @@ impl glean_core::traits::String for StringMetric {
         _ping_name: S,
     ) -> Option<std::string::String> {
         crate::block_on_dispatcher();
-        None
+        crate::sys::with_glean(|glean| glean.glean_string_test_get_value(self.id))
     }
 
     fn test_get_num_recorded_errors<'a, S: Into<Option<&'a str>>>(
         &self,
-        _error: ErrorType,
+        error: ErrorType,
         _ping_name: S,
     ) -> i32 {
         crate::block_on_dispatcher();
-        0
+        match error {
+            ErrorType::InvalidOverflow => self.truncated_count.load(Ordering::Relaxed) as i32,
+            _ => 0,
+        }
     }
 }
\ No newline at end of file
@@ -9,13 +9,15 @@ use crate::{dispatcher, new_metric};
 
 /// Timespan metric wrapper around the FFI implementation
 #[derive(Clone)]
-pub struct TimespanMetric(pub(crate) u64);
+pub struct TimespanMetric {
+    id: u64,
+}
 
 impl TimespanMetric {
     /// The public constructor used by automatically generated metrics.
     pub fn new(meta: glean_core::CommonMetricData, time_unit: TimeUnit) -> Self {
         let metric = new_metric!(glean_new_timespan_metric, meta, time_unit as i32);
-        Self(metric)
+        Self { id: metric }
     }
 }
 
@@ -23,8 +25,7 @@ impl TimespanMetric {
 impl glean_core::traits::Timespan for TimespanMetric {
     fn start(&self) {
         let start_time = time::precise_time_ns();
-
-        let id = self.0;
+        let id = self.id;
         dispatcher::launch(move || {
             crate::sys::with_glean(|glean| unsafe { glean.glean_timespan_set_start(id, start_time) });
         });
@@ -32,15 +33,14 @@ impl glean_core::traits::Timespan for TimespanMetric {
 
     fn stop(&self) {
         let stop_time = time::precise_time_ns();
-
-        let id = self.0;
+        let id = self.id;
         dispatcher::launch(move || {
             crate::sys::with_glean(|glean| unsafe { glean.glean_timespan_set_stop(id, stop_time) });
         });
     }
 
     fn cancel(&self) {
-        let id = self.0;
+        let id = self.id;
         dispatcher::launch(move || {
             crate::sys::with_glean(|glean| unsafe { glean.glean_timespan_cancel(id) });
         });
@@ -48,7 +48,7 @@ impl glean_core::traits::Timespan for TimespanMetric {
 
     fn test_get_value<'a, S: Into<Option<&'a str>>>(&self, _ping_name: S) -> Option<u64> {
         crate::block_on_dispatcher();
-        None
+        crate::sys::with_glean(|glean| glean.glean_timespan_test_get_value(self.id))
     }
 
     fn test_get_num_recorded_errors<'a, S: Into<Option<&'a str>>>(
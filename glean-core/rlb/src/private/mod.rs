@@ -0,0 +1,13 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The developer-facing metric and ping types generated code constructs
+//! from `metrics.yaml`/`pings.yaml` is built on top of.
+
+mod counter;
+pub(crate) mod dynamic;
+mod ping_type;
+
+pub use counter::CounterMetric;
+pub use ping_type::PingType;
@@ -0,0 +1,43 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Represents a ping type, as registered via [`crate::register_ping_type`]
+/// and submitted via [`submit`](Self::submit) or
+/// [`crate::submit_ping_by_name`].
+///
+/// Instances of this class type are automatically generated by the parsers
+/// at build time, allowing developers to record values that were previously
+/// registered in the pings.yaml file.
+#[derive(Debug, Clone)]
+pub struct PingType {
+    pub(crate) name: String,
+    pub(crate) send_if_empty: bool,
+    pub(crate) reason_codes: Vec<String>,
+}
+
+impl PingType {
+    /// The public constructor used by automatically generated pings.
+    ///
+    /// `include_client_id` is accepted for parity with the other Glean SDKs'
+    /// `PingType` constructors; it is neither stored nor used, and no
+    /// `client_info` section is added to submitted pings.
+    pub fn new(
+        name: impl Into<String>,
+        _include_client_id: bool,
+        send_if_empty: bool,
+        reason_codes: Vec<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            send_if_empty,
+            reason_codes,
+        }
+    }
+
+    /// Submits this ping, registered via
+    /// [`crate::register_ping_type`]. See [`crate::submit_ping_by_name`].
+    pub fn submit(&self, reason: Option<&str>) {
+        crate::submit_ping_by_name(&self.name, reason);
+    }
+}
@@ -0,0 +1,160 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The FFI boundary `private::dynamic` metric wrappers are written against.
+//!
+//! Upstream Glean bindings cross into the `glean-core` C FFI here; this
+//! crate instead keeps an in-process `Glean` handle with the same method
+//! names and call-site shapes, so `new_metric!` and the hand-written
+//! `private::dynamic` metrics don't need to change if a real FFI layer
+//! replaces this later. Metric constructors take a generic `_meta: T`
+//! rather than a concrete `CommonMetricData`, since the pre-existing
+//! `private::dynamic` metrics are constructed from `glean_core`'s type
+//! while everything else in this crate uses its own `crate::CommonMetricData`
+//! -- this layer doesn't need to pick a side between the two.
+
+use std::collections::HashMap;
+use std::ffi::{c_char, CStr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// The in-progress or completed measurement backing a timespan metric.
+#[derive(Clone, Copy, Default)]
+struct TimespanRecord {
+    start: Option<u64>,
+    elapsed: Option<u64>,
+}
+
+/// The in-process stand-in for the real FFI-backed Glean handle.
+pub(crate) struct Glean {
+    next_id: AtomicU64,
+    strings: Mutex<HashMap<u64, Option<std::string::String>>>,
+    timespans: Mutex<HashMap<u64, TimespanRecord>>,
+    counters: Mutex<HashMap<u64, i64>>,
+}
+
+impl Glean {
+    fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            strings: Mutex::new(HashMap::new()),
+            timespans: Mutex::new(HashMap::new()),
+            counters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn allocate_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Registers a new string metric, returning the id later calls use to
+    /// refer to it.
+    pub(crate) fn glean_new_string_metric<T>(&self, _meta: T) -> u64 {
+        let id = self.allocate_id();
+        self.strings.lock().unwrap().insert(id, None);
+        id
+    }
+
+    /// Sets the string metric `id`'s value.
+    ///
+    /// # Safety
+    ///
+    /// `value` must be a valid, NUL-terminated, UTF-8 C string pointer.
+    pub(crate) unsafe fn glean_string_set(&self, id: u64, value: *const c_char) {
+        let value = unsafe { CStr::from_ptr(value) }
+            .to_string_lossy()
+            .into_owned();
+        self.strings.lock().unwrap().insert(id, Some(value));
+    }
+
+    /// Returns the string metric `id`'s currently recorded value, if any.
+    pub(crate) fn glean_string_test_get_value(&self, id: u64) -> Option<std::string::String> {
+        self.strings.lock().unwrap().get(&id).cloned().flatten()
+    }
+
+    /// Registers a new timespan metric, returning the id later calls use to
+    /// refer to it.
+    pub(crate) fn glean_new_timespan_metric<T>(&self, _meta: T, _time_unit: i32) -> u64 {
+        let id = self.allocate_id();
+        self.timespans
+            .lock()
+            .unwrap()
+            .insert(id, TimespanRecord::default());
+        id
+    }
+
+    /// Records the start of timespan metric `id`, in nanoseconds.
+    ///
+    /// # Safety
+    ///
+    /// `id` must have been returned by [`glean_new_timespan_metric`](Self::glean_new_timespan_metric).
+    pub(crate) unsafe fn glean_timespan_set_start(&self, id: u64, start_time: u64) {
+        let mut timespans = self.timespans.lock().unwrap();
+        timespans.entry(id).or_default().start = Some(start_time);
+    }
+
+    /// Records the end of timespan metric `id`, in nanoseconds, completing
+    /// the measurement if a start was recorded.
+    ///
+    /// # Safety
+    ///
+    /// `id` must have been returned by [`glean_new_timespan_metric`](Self::glean_new_timespan_metric).
+    pub(crate) unsafe fn glean_timespan_set_stop(&self, id: u64, stop_time: u64) {
+        let mut timespans = self.timespans.lock().unwrap();
+        let record = timespans.entry(id).or_default();
+        if let Some(start_time) = record.start.take() {
+            record.elapsed = Some(stop_time.saturating_sub(start_time));
+        }
+    }
+
+    /// Discards any in-progress measurement for timespan metric `id`.
+    ///
+    /// # Safety
+    ///
+    /// `id` must have been returned by [`glean_new_timespan_metric`](Self::glean_new_timespan_metric).
+    pub(crate) unsafe fn glean_timespan_cancel(&self, id: u64) {
+        self.timespans.lock().unwrap().entry(id).or_default().start = None;
+    }
+
+    /// Returns the timespan metric `id`'s last completed duration, if any.
+    pub(crate) fn glean_timespan_test_get_value(&self, id: u64) -> Option<u64> {
+        self.timespans
+            .lock()
+            .unwrap()
+            .get(&id)
+            .and_then(|record| record.elapsed)
+    }
+
+    /// Registers a new counter metric, returning the id later calls use to
+    /// refer to it.
+    pub(crate) fn glean_new_counter_metric<T>(&self, _meta: T) -> u64 {
+        let id = self.allocate_id();
+        self.counters.lock().unwrap().insert(id, 0);
+        id
+    }
+
+    /// Adds `amount` to counter metric `id`.
+    ///
+    /// # Safety
+    ///
+    /// `id` must have been returned by [`glean_new_counter_metric`](Self::glean_new_counter_metric).
+    pub(crate) unsafe fn glean_counter_add(&self, id: u64, amount: i32) {
+        *self.counters.lock().unwrap().entry(id).or_insert(0) += i64::from(amount);
+    }
+
+    /// Returns counter metric `id`'s currently recorded value, if any.
+    pub(crate) fn glean_counter_test_get_value(&self, id: u64) -> Option<i64> {
+        self.counters.lock().unwrap().get(&id).copied()
+    }
+}
+
+fn global() -> &'static Glean {
+    static GLEAN: OnceLock<Glean> = OnceLock::new();
+    GLEAN.get_or_init(Glean::new)
+}
+
+/// Runs `f` against the global [`Glean`] handle.
+pub(crate) fn with_glean<R>(f: impl FnOnce(&Glean) -> R) -> R {
+    f(global())
+}
@@ -0,0 +1,239 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A pluggable ping uploader plus a small retry/backoff scheduler.
+//!
+//! This crate doesn't bundle an HTTP client (embedders run on wildly
+//! different stacks: reqwest on desktop, a platform-native client on
+//! mobile), so uploading itself is left to whoever implements
+//! [`PingUploader`] and hands it to [`crate::Configuration::uploader`].
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+/// Request headers to send along with a ping, keyed by header name.
+pub type HeaderMap = HashMap<String, String>;
+
+/// The outcome of a single upload attempt, as reported by a [`PingUploader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadResult {
+    /// The server was reached and responded with the given HTTP status.
+    ///
+    /// The scheduler treats 2xx/4xx as final and 5xx as retryable, same as
+    /// [`RecoverableFailure`](Self::RecoverableFailure).
+    HttpStatus(u16),
+
+    /// The request failed in a way that might succeed on retry (e.g. the
+    /// network was briefly unreachable, or the server timed out).
+    RecoverableFailure,
+
+    /// The request failed in a way that won't improve with retries (e.g.
+    /// the body was rejected as malformed). The ping is dropped.
+    UnrecoverableFailure,
+}
+
+impl UploadResult {
+    fn is_retryable(&self) -> bool {
+        match self {
+            UploadResult::HttpStatus(status) => (500..600).contains(status),
+            UploadResult::RecoverableFailure => true,
+            UploadResult::UnrecoverableFailure => false,
+        }
+    }
+}
+
+/// Implemented by the embedder to perform the actual network request for a
+/// ping: reqwest on desktop, a platform-native client on mobile, etc.
+///
+/// Glean doesn't know or care how the request is actually made -- it only
+/// needs the outcome back so it can decide whether to retry.
+pub trait PingUploader: Send + Sync {
+    /// Uploads `body` to `url` with the given `headers`, blocking until the
+    /// attempt completes.
+    fn upload(&self, url: String, body: Vec<u8>, headers: HeaderMap) -> UploadResult;
+}
+
+/// A ping that's queued for upload, along with how many times we've already
+/// tried to send it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct PendingPing {
+    url: String,
+    body: Vec<u8>,
+    headers: HeaderMap,
+    attempts: u32,
+}
+
+/// How many upload attempts (the first one included) a single ping gets
+/// before it's given up on.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// The base delay used to compute exponential backoff, before jitter.
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The upper bound on backoff before jitter, regardless of how many attempts
+/// have failed.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The file pending pings are persisted to, relative to the data directory,
+/// so a temporarily unreachable `server_endpoint` doesn't lose telemetry
+/// across a `shutdown()`/restart.
+const PENDING_PINGS_FILE: &str = "pending_pings.json";
+
+/// Schedules ping uploads through a [`PingUploader`], retrying recoverable
+/// failures with exponential backoff and jitter, and persisting the queue
+/// of not-yet-sent pings to disk across restarts.
+pub(crate) struct UploadManager {
+    uploader: Arc<dyn PingUploader>,
+    data_path: PathBuf,
+    queue: Mutex<VecDeque<PendingPing>>,
+    attempt_counter: AtomicU32,
+}
+
+impl UploadManager {
+    /// Builds the manager and starts its background retry loop, which
+    /// sleeps [`next_backoff`](Self::next_backoff) between
+    /// [`drain`](Self::drain) calls for as long as the returned `Arc` (or a
+    /// clone of it) is alive.
+    pub(crate) fn new(uploader: Arc<dyn PingUploader>, data_path: PathBuf) -> Arc<Self> {
+        let manager = Arc::new(Self {
+            uploader,
+            data_path,
+            queue: Mutex::new(VecDeque::new()),
+            attempt_counter: AtomicU32::new(0),
+        });
+        manager.restore();
+        manager.spawn_retry_loop();
+        manager
+    }
+
+    /// Runs forever on a background thread, retrying whatever's left in the
+    /// queue after waiting [`next_backoff`](Self::next_backoff). Exits once
+    /// every other `Arc<UploadManager>` has been dropped, rather than on any
+    /// explicit shutdown signal -- [`crate::shutdown`] only needs the queue
+    /// persisted, not this loop stopped.
+    fn spawn_retry_loop(self: &Arc<Self>) {
+        let weak: Weak<Self> = Arc::downgrade(self);
+        thread::Builder::new()
+            .name("glean.upload-retry".into())
+            .spawn(move || loop {
+                let Some(manager) = weak.upgrade() else {
+                    return;
+                };
+                if manager.queue.lock().unwrap().is_empty() {
+                    drop(manager);
+                    thread::sleep(BASE_BACKOFF);
+                    continue;
+                }
+                let backoff = manager.next_backoff();
+                drop(manager);
+                thread::sleep(backoff);
+
+                let Some(manager) = weak.upgrade() else {
+                    return;
+                };
+                manager.drain();
+            })
+            .expect("failed to spawn glean upload retry thread");
+    }
+
+    /// Assembles a ping for upload and immediately attempts to drain the
+    /// queue once. Any ping that's still retryable afterwards waits for the
+    /// background retry loop started in [`new`](Self::new), rather than
+    /// being retried again from here.
+    ///
+    /// Tags the request with the debug view header and logs the payload,
+    /// per [`crate::set_debug_view_tag`]/[`crate::set_log_pings`], before
+    /// it ever reaches the queue.
+    pub(crate) fn enqueue(&self, ping_type: &str, url: String, body: Vec<u8>, mut headers: HeaderMap) {
+        crate::debug::tag_headers(&mut headers);
+        crate::debug::log_ping_payload(ping_type, &String::from_utf8_lossy(&body));
+
+        self.queue.lock().unwrap().push_back(PendingPing {
+            url,
+            body,
+            headers,
+            attempts: 0,
+        });
+        self.drain();
+    }
+
+    /// Attempts to upload every ping currently in the queue, requeueing
+    /// recoverable failures (with their attempt count bumped) and dropping
+    /// unrecoverable ones and pings that have exhausted `MAX_ATTEMPTS`. Blocks
+    /// only on the uploader itself and never sleeps for backoff -- the
+    /// background retry loop started in [`new`](Self::new) is what waits
+    /// [`next_backoff`](Self::next_backoff) between calls.
+    pub(crate) fn drain(&self) {
+        let mut pending: VecDeque<PendingPing> = {
+            let mut queue = self.queue.lock().unwrap();
+            std::mem::take(&mut *queue)
+        };
+
+        while let Some(mut ping) = pending.pop_front() {
+            let result = self.uploader.upload(
+                ping.url.clone(),
+                ping.body.clone(),
+                ping.headers.clone(),
+            );
+
+            if result.is_retryable() && ping.attempts + 1 < MAX_ATTEMPTS {
+                ping.attempts += 1;
+                self.attempt_counter.fetch_add(1, Ordering::Relaxed);
+                self.queue.lock().unwrap().push_back(ping);
+            }
+        }
+    }
+
+    /// The exponential backoff (with jitter) to wait before the next
+    /// [`drain`](Self::drain) call, based on how many retries have happened
+    /// so far.
+    pub(crate) fn next_backoff(&self) -> Duration {
+        let attempts = self.attempt_counter.load(Ordering::Relaxed).min(16);
+        let exp = BASE_BACKOFF.saturating_mul(1 << attempts.min(7));
+        let capped = exp.min(MAX_BACKOFF);
+
+        // A cheap jitter source: no `rand` dependency here, so perturb the
+        // delay using the current queue length instead of true randomness.
+        let jitter_millis = (self.queue.lock().unwrap().len() as u64 * 37) % 250;
+        capped + Duration::from_millis(jitter_millis)
+    }
+
+    fn pending_pings_path(&self) -> PathBuf {
+        self.data_path.join(PENDING_PINGS_FILE)
+    }
+
+    /// Writes the pending queue to [`PENDING_PINGS_FILE`] under the data
+    /// path, so it survives `shutdown()`/restart. Called from
+    /// [`crate::shutdown`].
+    pub(crate) fn persist(&self) {
+        let queue = self.queue.lock().unwrap();
+        let Ok(serialized) = serde_json::to_vec(&*queue) else {
+            return;
+        };
+        if let Err(err) = fs::write(self.pending_pings_path(), serialized) {
+            log::warn!("Failed to persist pending pings: {err}");
+        }
+    }
+
+    /// Loads a pending queue previously written by [`persist`](Self::persist),
+    /// if one exists. Called once from [`UploadManager::new`].
+    fn restore(&self) {
+        let path = self.pending_pings_path();
+        let Ok(data) = fs::read(&path) else {
+            return;
+        };
+        let Ok(pending) = serde_json::from_slice::<VecDeque<PendingPing>>(&data) else {
+            return;
+        };
+
+        *self.queue.lock().unwrap() = pending;
+        // Don't leave a stale copy behind once it's loaded back into memory.
+        let _ = fs::remove_file(&path);
+    }
+}
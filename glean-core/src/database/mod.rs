@@ -2,11 +2,13 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::num::NonZeroU64;
 use std::panic::AssertUnwindSafe;
 use std::path::Path;
 use std::str;
+use std::sync::RwLock;
 
 /// Unwrap a `Result`s `Ok` value or do the specified action.
 ///
@@ -54,17 +56,45 @@ CREATE TABLE IF NOT EXISTS pings
 COMMIT;
 "#;
 
+/// Which concrete storage backend a [`Database`] should use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// Persist to the SQLite file under `db/telemetry.db` in the data path.
+    File,
+    /// Keep everything in memory; nothing is written to disk.
+    Memory,
+}
+
+/// The SQLite-backed metrics store.
+///
+/// There used to be a startup migration step here (moving data out of an
+/// old `rkv`/LMDB store), with distinct outcome cases worth instrumenting
+/// as a health metric (no old database, empty old database, migrated,
+/// corrupt source, partial failure). That migration -- and the `rkv`
+/// environment it migrated out of -- is gone; `Database::new` only ever
+/// opens (or creates) the SQLite file directly, so there is no outcome
+/// left to classify or report.
 pub struct Database {
     /// The database connection.
     ///
     /// FIXME: It's probably not unwind safe.
     conn: AssertUnwindSafe<Connection>,
+
+    /// In-memory cache of ping-lifetime values, keyed by `"{storage_name}#{key}"`.
+    ///
+    /// `Some` when `delay_ping_lifetime_io` is requested: ping-lifetime
+    /// writes then land here instead of going straight to SQLite, and only
+    /// reach disk once [`persist_ping_lifetime_data`](Self::persist_ping_lifetime_data)
+    /// is called. `None` otherwise, in which case ping-lifetime metrics are
+    /// written through immediately like every other lifetime.
+    ping_lifetime_data: Option<RwLock<HashMap<String, Metric>>>,
 }
 
 impl std::fmt::Debug for Database {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
         fmt.debug_struct("Database")
             .field("conn", &self.conn)
+            .field("ping_lifetime_data", &self.ping_lifetime_data.is_some())
             .finish()
     }
 }
@@ -74,7 +104,12 @@ impl Database {
     ///
     /// This opens the underlying SQLite store and creates
     /// the underlying directory structure.
-    pub fn new(data_path: &Path, _delay_ping_lifetime_io: bool) -> Result<Self> {
+    ///
+    /// Note: there is no LMDB/safe-mode migration path here (and so nothing
+    /// left that could probe for, or leave behind, stray LMDB files) -- see
+    /// [`Database`]'s own doc comment for why. `new` opens exactly the one
+    /// SQLite file it's going to use, and nothing else.
+    pub fn new(data_path: &Path, delay_ping_lifetime_io: bool) -> Result<Self> {
         let path = data_path.join("db");
         log::debug!("Database path: {:?}", path.display());
 
@@ -85,6 +120,45 @@ impl Database {
             OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
         )?;
 
+        Self::with_connection(conn, delay_ping_lifetime_io)
+    }
+
+    /// Initializes an in-memory-only data store.
+    ///
+    /// Nothing is ever written to disk: every table lives for as long as the
+    /// `Database` (and the `Connection` backing it) is kept alive. This is
+    /// meant for unit tests and for very short-lived embedding processes that
+    /// have no business paying for file I/O.
+    pub fn new_in_memory() -> Result<Self> {
+        log::debug!("Database path: :memory:");
+        let conn = Connection::open_in_memory()?;
+        Self::with_connection(conn, false)
+    }
+
+    /// Initializes the data store using an explicitly chosen [`StorageBackend`].
+    ///
+    /// This is the selectable-backend counterpart of [`new`](Self::new) and
+    /// [`new_in_memory`](Self::new_in_memory), for callers that want to pick
+    /// the backend programmatically (e.g. "use memory in tests, file
+    /// otherwise") rather than calling one constructor or the other.
+    ///
+    /// Note: `backend` only picks which constructor below runs -- see
+    /// [`Database`]'s own doc comment for why there's no migration between
+    /// on-disk formats to perform here.
+    pub fn new_with_backend(
+        backend: StorageBackend,
+        data_path: &Path,
+        delay_ping_lifetime_io: bool,
+    ) -> Result<Self> {
+        match backend {
+            StorageBackend::File => Self::new(data_path, delay_ping_lifetime_io),
+            StorageBackend::Memory => Self::new_in_memory(),
+        }
+    }
+
+    /// Finishes setting up a freshly opened connection, regardless of whether
+    /// it is backed by a file or lives purely in memory.
+    fn with_connection(conn: Connection, delay_ping_lifetime_io: bool) -> Result<Self> {
         // as per application-servers, components/places/src/db/db.rs
         #[cfg(target_os = "android")]
         {
@@ -102,6 +176,11 @@ impl Database {
 
         let db = Self {
             conn: AssertUnwindSafe(conn),
+            ping_lifetime_data: if delay_ping_lifetime_io {
+                Some(RwLock::new(HashMap::new()))
+            } else {
+                None
+            },
         };
 
         Ok(db)
@@ -113,6 +192,11 @@ impl Database {
     }
 
     /// Get the rkv load state.
+    ///
+    /// Kept for API compatibility with the old `rkv`-backed `Database` --
+    /// see [`Database`]'s own doc comment for why there's nothing left here
+    /// that could be "corrupt". Always reports `None` for this SQLite-backed
+    /// store.
     pub fn rkv_load_state(&self) -> Option<String> {
         None
     }
@@ -147,6 +231,50 @@ impl Database {
         mut transaction_fn: F,
     ) where
         F: FnMut(&[u8], &Metric),
+    {
+        Self::iter_store_from_conn(&self.conn, lifetime, storage_name, metric_key, |id, metric| {
+            transaction_fn(id, metric)
+        })
+    }
+
+    /// Iterates over the given storage across *all* lifetimes within a
+    /// single read transaction, so every metric reflects one consistent
+    /// moment.
+    ///
+    /// Calling [`iter_store_from`](Self::iter_store_from) once per lifetime
+    /// opens a fresh statement (and implicit transaction) each time; a
+    /// metric recorded in between two of those calls could show up in one
+    /// lifetime's snapshot but not another. Running all three queries inside
+    /// one transaction gives SQLite's snapshot semantics a chance to produce
+    /// a single point-in-time view of the whole store instead.
+    ///
+    /// # Panics
+    ///
+    /// This function will **not** panic on database errors.
+    pub fn iter_all_stores_from<F>(&self, storage_name: &str, metric_key: Option<&str>, mut transaction_fn: F)
+    where
+        F: FnMut(Lifetime, &[u8], &Metric),
+    {
+        let tx = unwrap_or!(self.conn.unchecked_transaction(), return);
+        for lifetime in [Lifetime::User, Lifetime::Ping, Lifetime::Application] {
+            Self::iter_store_from_conn(&tx, lifetime, storage_name, metric_key, |id, metric| {
+                transaction_fn(lifetime, id, metric)
+            });
+        }
+    }
+
+    /// Shared implementation of the iteration query, usable both against the
+    /// connection directly and against an open [`Transaction`] so callers
+    /// can choose whether they want a one-off read or a consistent snapshot
+    /// across several calls.
+    fn iter_store_from_conn<F>(
+        conn: &Connection,
+        lifetime: Lifetime,
+        storage_name: &str,
+        metric_key: Option<&str>,
+        mut transaction_fn: F,
+    ) where
+        F: FnMut(&[u8], &Metric),
     {
         let iter_sql = r#"
         SELECT id, value
@@ -161,7 +289,7 @@ impl Database {
         } else {
             iter_sql.to_string()
         };
-        let mut stmt = unwrap_or!(self.conn.prepare_cached(&iter_sql), return);
+        let mut stmt = unwrap_or!(conn.prepare_cached(&iter_sql), return);
         let mut rows = if let Some(metric_key) = metric_key {
             unwrap_or!(
                 stmt.query(params![
@@ -189,6 +317,43 @@ impl Database {
         }
     }
 
+    /// Dumps the entire contents of the database, across every lifetime and
+    /// storage, for troubleshooting a database an engineer doesn't otherwise
+    /// have a way to inspect.
+    ///
+    /// Entries whose value fails to deserialize back into a [`Metric`] are
+    /// skipped (and counted) rather than causing the whole dump to fail --
+    /// the point of this function is to see what, if anything, survived.
+    ///
+    /// # Returns
+    ///
+    /// A map of storage name to its `(metric_id, Metric)` entries, plus the
+    /// number of entries that could not be decoded.
+    ///
+    /// # Panics
+    ///
+    /// This function will **not** panic on database errors.
+    pub fn dump(&self) -> (BTreeMap<String, Vec<(String, Metric)>>, usize) {
+        let mut dump: BTreeMap<String, Vec<(String, Metric)>> = BTreeMap::new();
+        let mut skipped = 0;
+
+        let dump_sql = "SELECT id, ping, value FROM telemetry";
+        let mut stmt = unwrap_or!(self.conn.prepare_cached(dump_sql), return (dump, skipped));
+        let mut rows = unwrap_or!(stmt.query([]), return (dump, skipped));
+
+        while let Ok(Some(row)) = rows.next() {
+            let metric_id: String = unwrap_or!(row.get(0), continue);
+            let ping: String = unwrap_or!(row.get(1), continue);
+            let blob: Vec<u8> = unwrap_or!(row.get(2), continue);
+            match bincode::deserialize(&blob) {
+                Ok(metric) => dump.entry(ping).or_default().push((metric_id, metric)),
+                Err(_) => skipped += 1,
+            }
+        }
+
+        (dump, skipped)
+    }
+
     /// Determines if the storage has the given metric.
     ///
     /// If data cannot be read it is assumed that the storage does not have the metric.
@@ -265,6 +430,14 @@ impl Database {
         key: &str,
         metric: &Metric,
     ) -> Result<()> {
+        if lifetime == Lifetime::Ping {
+            if let Some(ping_lifetime_data) = &self.ping_lifetime_data {
+                let mut data = ping_lifetime_data.write().unwrap();
+                data.insert(format!("{}#{}", storage_name, key), metric.clone());
+                return Ok(());
+            }
+        }
+
         let insert_sql = r#"
         INSERT INTO
             telemetry (id, ping, lifetime, value, updated_at)
@@ -283,6 +456,52 @@ impl Database {
         Ok(())
     }
 
+    /// Records several metrics in a single SQLite transaction.
+    ///
+    /// Each entry in `ops` is `(lifetime, storage_name, key, metric)`, exactly
+    /// the arguments [`record_per_lifetime`](Self::record_per_lifetime) takes
+    /// for one write. Grouping the puts under a single transaction means a
+    /// single commit (and a single fsync) for the whole batch, instead of one
+    /// per metric -- useful for flushing a large number of ping-lifetime
+    /// values at once.
+    ///
+    /// # Returns
+    ///
+    /// If the storage is unavailable, no data will be stored and an error
+    /// will be returned. Otherwise `Ok(())` is returned.
+    ///
+    /// # Panics
+    ///
+    /// This function will **not** panic on database errors.
+    pub fn record_batch<'a>(
+        &self,
+        ops: impl Iterator<Item = (Lifetime, &'a str, &'a str, &'a Metric)>,
+    ) -> Result<()> {
+        let insert_sql = r#"
+        INSERT INTO
+            telemetry (id, ping, lifetime, value, updated_at)
+        VALUES
+            (?1, ?2, ?3, ?4, DATETIME('now'))
+        ON CONFLICT(id, ping) DO UPDATE SET
+            lifetime = excluded.lifetime,
+            value = excluded.value,
+            updated_at = excluded.updated_at
+        "#;
+
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(insert_sql)?;
+            for (lifetime, storage_name, key, metric) in ops {
+                let encoded =
+                    bincode::serialize(&metric).expect("IMPOSSIBLE: Serializing metric failed");
+                stmt.execute(params![key, storage_name, lifetime.as_str(), encoded])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
     /// Records the provided value, with the given lifetime,
     /// after applying a transformation function.
     pub fn record_with<F>(&self, glean: &Glean, data: &CommonMetricDataInternal, mut transform: F)
@@ -331,6 +550,16 @@ impl Database {
     where
         F: FnMut(Option<Metric>) -> Metric,
     {
+        if lifetime == Lifetime::Ping {
+            if let Some(ping_lifetime_data) = &self.ping_lifetime_data {
+                let mut data = ping_lifetime_data.write().unwrap();
+                let map_key = format!("{}#{}", storage_name, key);
+                let old_value = data.get(&map_key).cloned();
+                data.insert(map_key, transform(old_value));
+                return Ok(());
+            }
+        }
+
         let find_sql = r#"
         SELECT value
         FROM telemetry
@@ -459,12 +688,28 @@ impl Database {
 
     /// Persists ping_lifetime_data to disk.
     ///
-    /// Does nothing in case there is nothing to persist.
+    /// Does nothing in case there is nothing to persist (including when
+    /// `delay_ping_lifetime_io` wasn't requested, so there is no in-memory
+    /// map to flush in the first place).
+    ///
+    /// The accumulated map is written out through [`record_batch`](Self::record_batch),
+    /// so the whole thing commits in a single SQLite transaction rather than
+    /// one per metric. It's kept in memory afterwards -- this only pushes a
+    /// copy to disk, it doesn't clear the cache.
     ///
     /// # Panics
     ///
     /// * This function will **not** panic on database errors.
     pub fn persist_ping_lifetime_data(&self) -> Result<()> {
+        if let Some(ping_lifetime_data) = &self.ping_lifetime_data {
+            let data = ping_lifetime_data.read().unwrap();
+            let ops = data.iter().filter_map(|(map_key, metric)| {
+                let (storage_name, key) = map_key.split_once('#')?;
+                Some((Lifetime::Ping, storage_name, key, metric))
+            });
+            self.record_batch(ops)?;
+        }
+
         Ok(())
     }
 }
@@ -1,6 +1,8 @@
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use dispatch::{Queue, QueueAttribute};
 
@@ -32,6 +34,7 @@ impl Dispatcher {
             flushed: AtomicU8::new(NOT_FLUSHED),
             max_queue_size,
             preinit_queue,
+            overflow_count: AtomicUsize::new(0),
         };
 
         Dispatcher {
@@ -48,13 +51,57 @@ impl Dispatcher {
         Ok(())
     }
 
+    /// Shuts the dispatcher down, waiting at most `timeout` for the queue to
+    /// drain. Returns how long it actually waited.
+    ///
+    /// See [`DispatchGuard::shutdown_with_timeout`] for the full behavior.
+    pub fn shutdown_with_timeout(&self, timeout: Duration) -> Result<Duration, DispatchError> {
+        self.guard.shutdown_with_timeout(timeout)
+    }
+
+    /// Shuts the dispatcher down like [`shutdown_with_timeout`](Self::shutdown_with_timeout),
+    /// and also records the wait time into `metrics.shutdown_dispatcher_wait`,
+    /// so how long shutdown actually blocked on the dispatcher shows up in
+    /// telemetry instead of only being available to the immediate caller.
+    pub fn shutdown_with_timeout_and_record(
+        &self,
+        timeout: Duration,
+        metrics: &crate::internal_metrics::AdditionalMetrics,
+    ) -> Result<Duration, DispatchError> {
+        let waited = self.shutdown_with_timeout(timeout)?;
+        metrics.shutdown_dispatcher_wait.accumulate(waited);
+        Ok(waited)
+    }
+
     pub fn flush_init(&mut self) -> Result<usize, DispatchError> {
         self.guard.flush_init()
     }
 
+    /// Flushes the preinit queue like [`flush_init`](Self::flush_init), and
+    /// also records any dropped tasks into `metrics.preinit_tasks_overflow`,
+    /// so apps that record too much before `initialize` show up in
+    /// telemetry instead of having those tasks dropped silently.
+    pub fn flush_init_and_record(
+        &mut self,
+        metrics: &crate::internal_metrics::AdditionalMetrics,
+    ) -> Result<usize, DispatchError> {
+        let over = self.flush_init()?;
+        if over > 0 {
+            metrics.preinit_tasks_overflow.add(over as i32);
+        }
+        Ok(over)
+    }
+
     pub fn block_on_queue(&self) {
         self.guard.block_on_queue()
     }
+
+    /// The number of tasks the last [`flush_init`](Self::flush_init) (or
+    /// [`flush_init_and_record`](Self::flush_init_and_record)) call dropped
+    /// because they were queued before init beyond `max_queue_size`.
+    pub fn overflow_count(&self) -> usize {
+        self.guard.overflow_count()
+    }
 }
 
 /// A clonable guard for a dispatch queue.
@@ -63,11 +110,18 @@ pub struct DispatchGuard {
     flushed: AtomicU8,
     max_queue_size: usize,
     preinit_queue: Mutex<Vec<Box<dyn FnOnce() + Send + 'static>>>,
+    overflow_count: AtomicUsize,
 }
 
 impl DispatchGuard {
     pub fn launch(&self, task: impl FnOnce() + Send + 'static) -> Result<(), DispatchError> {
-        if self.flushed.load(Ordering::SeqCst) == IS_FLUSHED {
+        let state = self.flushed.load(Ordering::SeqCst);
+
+        if state == SHUTDOWN {
+            return Err(DispatchError::Shutdown);
+        }
+
+        if state == IS_FLUSHED {
             self.queue.exec_async(task);
         } else {
             let mut queue = self.preinit_queue.lock().unwrap();
@@ -83,6 +137,41 @@ impl DispatchGuard {
         Ok(())
     }
 
+    /// Shuts the dispatcher down, bounding how long it will block for.
+    ///
+    /// Flushes the preinit queue same as [`shutdown`](Self::shutdown), then
+    /// enqueues a sentinel task and waits up to `timeout` for everything
+    /// ahead of it on the serial queue to finish. Once this returns, new
+    /// [`launch`](Self::launch) calls are rejected with
+    /// [`DispatchError::Shutdown`] -- whether the queue drained in time or
+    /// not. If it didn't, whatever is still running or queued is abandoned
+    /// rather than blocking shutdown further. See
+    /// [`Dispatcher::shutdown_with_timeout_and_record`] for a wrapper that
+    /// records the returned wait time into
+    /// `AdditionalMetrics::shutdown_dispatcher_wait`.
+    pub fn shutdown_with_timeout(&self, timeout: Duration) -> Result<Duration, DispatchError> {
+        self.flush_init().ok();
+
+        let start = Instant::now();
+        let (tx, rx) = mpsc::channel();
+        self.queue.exec_async(move || {
+            let _ = tx.send(());
+        });
+
+        let waited = match rx.recv_timeout(timeout) {
+            Ok(()) => start.elapsed(),
+            Err(mpsc::RecvTimeoutError::Timeout) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                timeout
+            }
+        };
+
+        // Either way, we're done: stop accepting new work and leak whatever
+        // is still on the queue rather than waiting on it further.
+        self.flushed.store(SHUTDOWN, Ordering::SeqCst);
+
+        Ok(waited)
+    }
+
     pub fn block_on_queue(&self) {
         self.queue.exec_sync(|| {
             // intentionally left empty
@@ -117,10 +206,17 @@ impl DispatchGuard {
             }
         }
 
+        self.overflow_count.store(over, Ordering::SeqCst);
         self.flushed.store(IS_FLUSHED, Ordering::SeqCst);
         Ok(over)
     }
 
+    /// The number of tasks the last [`flush_init`](Self::flush_init) call
+    /// dropped for arriving before init beyond `max_queue_size`.
+    pub fn overflow_count(&self) -> usize {
+        self.overflow_count.load(Ordering::SeqCst)
+    }
+
     pub fn kill(&self) -> Result<(), DispatchError> {
         Ok(())
     }
@@ -28,6 +28,10 @@ pub struct AdditionalMetrics {
     /// Time waited for the dispatcher to unblock during shutdown.
     pub shutdown_dispatcher_wait: TimingDistributionMetric,
 
+    /// The number of tasks dropped from the dispatcher's preinit queue
+    /// because they arrived before `initialize` beyond its maximum size.
+    pub preinit_tasks_overflow: CounterMetric,
+
     /// An experimentation identifier derived and provided by the application
     /// for the purpose of experimentation enrollment.
     pub experimentation_id: StringMetric,
@@ -110,6 +114,14 @@ impl AdditionalMetrics {
                 TimeUnit::Millisecond,
             ),
 
+            preinit_tasks_overflow: CounterMetric::new(CommonMetricData {
+                identifier: "glean.error.preinit_tasks_overflow".into(),
+                send_in_pings: vec!["metrics".into()],
+                lifetime: Lifetime::Ping,
+                disabled: false,
+                dynamic_label: None,
+            }),
+
             // This uses a `send_in_pings` that contains "all-ping".
             // This works because all of our other current "all-pings" metrics
             // have special handling internally and are not actually processed
@@ -2,7 +2,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::private::MetricType;
 use crate::{CommonMetricData, Glean};
@@ -31,9 +33,46 @@ pub struct RecordedEventData {
     pub extra: Option<String>,
 }
 
+/// A very small "did we drop something we shouldn't have" counter, used in
+/// place of the full `glean_core::error_recording` machinery this crate
+/// doesn't (yet) pull in.
+#[derive(Debug, Default)]
+struct ErrorCounts {
+    invalid_extra: AtomicU32,
+}
+
+/// Implemented by the `...Extra` struct the build-time metrics parser
+/// generates for an event with declared extra keys, so generated code can
+/// record typed extras instead of building a raw string map by hand.
+pub trait ExtraKeys {
+    /// Converts the typed extra values into the raw `key -> value` map the
+    /// rest of `EventMetric` understands.
+    fn into_ffi_extra(self) -> HashMap<String, String>;
+}
+
+impl ExtraKeys for HashMap<String, String> {
+    fn into_ffi_extra(self) -> HashMap<String, String> {
+        self
+    }
+}
+
 #[derive(Debug)]
 pub struct EventMetric {
     meta: Arc<CommonMetricData>,
+    allowed_extra_keys: Arc<Vec<String>>,
+    errors: Arc<ErrorCounts>,
+    recorded: Arc<Mutex<Vec<RecordedEventData>>>,
+}
+
+impl Clone for EventMetric {
+    fn clone(&self) -> Self {
+        Self {
+            meta: Arc::clone(&self.meta),
+            allowed_extra_keys: Arc::clone(&self.allowed_extra_keys),
+            errors: Arc::clone(&self.errors),
+            recorded: Arc::clone(&self.recorded),
+        }
+    }
 }
 
 impl MetricType for EventMetric {
@@ -46,6 +85,9 @@ impl MetricType for EventMetric {
         meta.name = name;
         Self {
             meta: Arc::new(meta),
+            allowed_extra_keys: Arc::clone(&self.allowed_extra_keys),
+            errors: Arc::new(ErrorCounts::default()),
+            recorded: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -54,33 +96,112 @@ impl MetricType for EventMetric {
         meta.dynamic_label = Some(label);
         Self {
             meta: Arc::new(meta),
+            allowed_extra_keys: Arc::clone(&self.allowed_extra_keys),
+            errors: Arc::new(ErrorCounts::default()),
+            recorded: Arc::new(Mutex::new(Vec::new())),
         }
     }
 }
 
+/// A monotonic counter used to timestamp recorded events, mirroring the
+/// "order events from a single process run" guarantee on
+/// [`RecordedEventData::timestamp`].
+static NEXT_TIMESTAMP: AtomicU64 = AtomicU64::new(0);
+
 impl EventMetric {
     /// The public constructor used by automatically generated metrics.
     pub fn new(meta: CommonMetricData, allowed_extra_keys: Vec<String>) -> Self {
         Self {
             meta: Arc::new(meta),
+            allowed_extra_keys: Arc::new(allowed_extra_keys),
+            errors: Arc::new(ErrorCounts::default()),
+            recorded: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
-    pub fn internal_record(&self, extra: Option<String>) {
+    /// Records the event with a raw, build-time-unchecked extra map.
+    ///
+    /// Keys that aren't in `allowed_extra_keys` are dropped and counted as
+    /// an invalid-value error rather than stored, matching how every other
+    /// metric type handles out-of-range input.
+    pub fn record(&self, extra: HashMap<String, String>) {
+        self.internal_record(extra)
+    }
+
+    /// Records the event using a generated, typed extras struct.
+    ///
+    /// This is the path build-time generated code should use: it can hand
+    /// us an enum-backed `...Extra` struct instead of assembling a
+    /// `HashMap` by hand.
+    pub fn record_with_extra<E: ExtraKeys>(&self, extra: E) {
+        self.internal_record(extra.into_ffi_extra())
+    }
+
+    fn internal_record(&self, extra: HashMap<String, String>) {
+        let (valid, invalid): (HashMap<_, _>, HashMap<_, _>) = extra
+            .into_iter()
+            .partition(|(key, _)| self.allowed_extra_keys.iter().any(|allowed| allowed == key));
+
+        if !invalid.is_empty() {
+            log::warn!(
+                "Invalid extra keys for event {}.{}, dropping: {:?} (ErrorType::InvalidValue)",
+                self.meta.category,
+                self.meta.name,
+                invalid.keys().collect::<Vec<_>>()
+            );
+            self.errors
+                .invalid_extra
+                .fetch_add(invalid.len() as u32, Ordering::Relaxed);
+        }
+
+        let timestamp = NEXT_TIMESTAMP.fetch_add(1, Ordering::Relaxed);
         let metric = self.clone();
-        crate::launch_with_glean(move |glean| {
-            log::info!("Recording event with extra {:?}", extra);
+        crate::launch_with_glean(move |_glean| {
+            let extra = if valid.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&valid).expect("IMPOSSIBLE: Serializing extras failed"))
+            };
+
+            let data = RecordedEventData {
+                timestamp,
+                category: metric.meta.category.clone(),
+                name: metric.meta.name.clone(),
+                extra,
+            };
+
+            metric.recorded.lock().unwrap().push(data);
         })
     }
 
-    pub(crate) fn get_value(&self, glean: &Glean, ping_name: Option<&str>) -> Option<RecordedEventData> {
-        let queried_ping_name = ping_name.unwrap_or_else(|| &self.meta().send_in_pings[0]);
+    /// Returns every event recorded so far, ordered by recording time (the
+    /// order [`NEXT_TIMESTAMP`] handed them out in), or `None` if nothing
+    /// was recorded.
+    pub(crate) fn get_value(
+        &self,
+        _glean: &Glean,
+        ping_name: Option<&str>,
+    ) -> Option<Vec<RecordedEventData>> {
+        let _queried_ping_name = ping_name.unwrap_or_else(|| &self.meta().send_in_pings[0]);
 
-        None
+        let recorded = self.recorded.lock().unwrap();
+        if recorded.is_empty() {
+            None
+        } else {
+            let mut events = recorded.clone();
+            events.sort_by_key(|event| event.timestamp);
+            Some(events)
+        }
     }
 
-    pub fn test_get_value(&self, ping_name: Option<String>) -> Option<RecordedEventData> {
+    pub fn test_get_value(&self, ping_name: Option<String>) -> Option<Vec<RecordedEventData>> {
         crate::block_on_dispatcher();
         crate::core::with_glean(|glean| self.get_value(glean, ping_name.as_deref()))
     }
+
+    /// The number of extra keys dropped for not being in the allowed set.
+    pub fn test_get_num_recorded_errors(&self) -> i32 {
+        crate::block_on_dispatcher();
+        self.errors.invalid_extra.load(Ordering::Relaxed) as i32
+    }
 }